@@ -1,22 +1,80 @@
-use jni::objects::{JByteBuffer, JClass, JIntArray, JLongArray, ReleaseMode};
+use jni::objects::{
+    JByteArray, JByteBuffer, JClass, JIntArray, JLongArray, JObjectArray, JString, ReleaseMode,
+};
 use jni::sys::{jboolean, jint, jlong};
 use jni::JNIEnv;
 
+use arc_swap::ArcSwap;
 use once_cell::sync::Lazy;
 use parking_lot::RwLock;
-use regex::bytes::Regex;
+use regex::bytes::{Regex, RegexSet};
+use rayon::prelude::*;
+use regex_automata::dfa::{dense::DFA, Automaton};
+use regex_automata::{Anchored, Input};
 use slab::Slab;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
-static REGEX_CACHE: Lazy<moka::sync::Cache<String, Arc<Regex>>> = Lazy::new(|| {
-    moka::sync::Cache::builder()
-        .build()
-});
+// Rebuilt wholesale by `configureCache`; an `ArcSwap` lets `compile` keep reading the old
+// cache lock-free while a reconfiguration swaps in a freshly-bounded one. `HANDLES` keeps
+// its own `Arc<Regex>` clone per live handle, so evicting (or replacing) a cache entry is
+// always safe for handles that are already in use.
+static REGEX_CACHE: Lazy<ArcSwap<moka::sync::Cache<String, Arc<Regex>>>> =
+    Lazy::new(|| ArcSwap::from_pointee(moka::sync::Cache::builder().build()));
+
+static CACHE_HITS: AtomicU64 = AtomicU64::new(0);
+static CACHE_MISSES: AtomicU64 = AtomicU64::new(0);
+
+// Mirrors CACHE_HITS/CACHE_MISSES but for REGEXSET_CACHE; kept separate (not folded into
+// the same counters) so `cacheStats` can report each cache's hit rate independently.
+static SET_CACHE_HITS: AtomicU64 = AtomicU64::new(0);
+static SET_CACHE_MISSES: AtomicU64 = AtomicU64::new(0);
 
 static HANDLES: Lazy<RwLock<Slab<Arc<Regex>>>> = Lazy::new(|| RwLock::new(Slab::with_capacity(1024)));
 
+// Same unbounded-growth risk as REGEX_CACHE applies to compileSet's dynamic patterns, so
+// it is rebuilt by `configureCache` the same way, behind its own `ArcSwap`.
+static REGEXSET_CACHE: Lazy<ArcSwap<moka::sync::Cache<Vec<String>, Arc<RegexSet>>>> =
+    Lazy::new(|| ArcSwap::from_pointee(moka::sync::Cache::builder().build()));
+
+static SET_HANDLES: Lazy<RwLock<Slab<Arc<RegexSet>>>> =
+    Lazy::new(|| RwLock::new(Slab::with_capacity(256)));
+
+// A memory-mapped dense DFA's `Automaton` impl borrows its transition table straight out
+// of the mapped bytes, hence the `&'static [u32]`; aliased since the full type shows up in
+// every handle-table/lookup signature below.
+type DfaHandle = Arc<DFA<&'static [u32]>>;
+
+// Memory-mapped dense DFAs loaded zero-copy via `loadDfa`. Handles into this table are
+// negative (see `dfa_handle_to_index`) so `matchesUtf8Direct` can tell a DFA handle apart
+// from an ordinary `Arc<Regex>` handle without a separate Java-side API.
+static DFA_HANDLES: Lazy<RwLock<Slab<DfaHandle>>> =
+    Lazy::new(|| RwLock::new(Slab::with_capacity(64)));
+
+// `HANDLES` and `SET_HANDLES` are independent slabs, so their handle values must be
+// partitioned the same way DFA handles are (see `dfa_handle_to_index` below) or a
+// `Regex` handle and a `RegexSet` handle could collide on the same positive integer.
+// Regex handles are the odd positive values (1, 3, 5, ...), RegexSet handles the even
+// positive values (2, 4, 6, ...); DFA handles stay negative.
 fn handle_to_index(handle: jlong) -> Option<usize> {
-    if handle <= 0 { None } else { Some((handle as usize) - 1) }
+    if handle <= 0 || handle % 2 == 0 { None } else { Some(((handle - 1) / 2) as usize) }
+}
+
+fn encode_handle(idx: usize) -> jlong {
+    (idx as jlong) * 2 + 1
+}
+
+fn set_handle_to_index(handle: jlong) -> Option<usize> {
+    if handle <= 0 || handle % 2 != 0 { None } else { Some((handle / 2 - 1) as usize) }
+}
+
+fn encode_set_handle(idx: usize) -> jlong {
+    (idx as jlong + 1) * 2
+}
+
+fn dfa_handle_to_index(handle: jlong) -> Option<usize> {
+    if handle >= 0 { None } else { Some((-handle as usize) - 1) }
 }
 
 fn get_regex_from_handle(handle: jlong) -> Option<Arc<Regex>> {
@@ -25,10 +83,80 @@ fn get_regex_from_handle(handle: jlong) -> Option<Arc<Regex>> {
     table.get(idx).cloned()
 }
 
+fn get_regexset_from_handle(handle: jlong) -> Option<Arc<RegexSet>> {
+    let idx = set_handle_to_index(handle)?;
+    let table = SET_HANDLES.read();
+    table.get(idx).cloned()
+}
+
+fn get_dfa_from_handle(handle: jlong) -> Option<DfaHandle> {
+    let idx = dfa_handle_to_index(handle)?;
+    let table = DFA_HANDLES.read();
+    table.get(idx).cloned()
+}
+
 fn throw_iae(env: &mut JNIEnv, msg: &str) {
     let _ = env.throw_new("java/lang/IllegalArgumentException", msg);
 }
 
+// Either kind of handle that can answer a match query: a compiled `Regex` or a
+// memory-mapped dense DFA. Shared by every `*Utf8Direct` entry point that needs to work
+// against both handle spaces (see the handle-partitioning note above `handle_to_index`).
+enum Matcher {
+    Regex(Arc<Regex>),
+    Dfa(DfaHandle),
+}
+
+fn resolve_matcher(handle: jlong) -> Option<Matcher> {
+    if let Some(dfa) = get_dfa_from_handle(handle) {
+        Some(Matcher::Dfa(dfa))
+    } else {
+        get_regex_from_handle(handle).map(Matcher::Regex)
+    }
+}
+
+// Validates `[offset, offset + len)` against a DirectByteBuffer's address/capacity and
+// returns the zero-copy slice over it. Every `*Utf8Direct` entry point that takes a
+// single `(buffer, offset, len)` triple goes through this instead of re-deriving the
+// pointer arithmetic and bounds check by hand.
+//
+// Safety: the returned slice borrows native memory owned by the Java-side
+// DirectByteBuffer, not by `env` or `buf`; the caller must keep that buffer alive for as
+// long as the slice (or anything derived from it) is in use, the same zero-copy contract
+// every direct-buffer entry point in this file relies on.
+fn buffer_slice<'a>(
+    env: &mut JNIEnv,
+    buf: &JByteBuffer,
+    offset: jint,
+    len: jint,
+) -> Result<&'a [u8], ()> {
+    let base_ptr = match env.get_direct_buffer_address(buf) {
+        Ok(p) => p,
+        Err(_) => {
+            throw_iae(env, "Buffer is not a DirectByteBuffer");
+            return Err(());
+        }
+    };
+    let cap = match env.get_direct_buffer_capacity(buf) {
+        Ok(c) => c as usize,
+        Err(_) => {
+            throw_iae(env, "Failed to read DirectByteBuffer capacity");
+            return Err(());
+        }
+    };
+
+    let off_u = offset as usize;
+    let ln_u = len as usize;
+
+    // Fast unsigned bounds check covering both negative values and overflows.
+    if off_u > cap || ln_u > cap - off_u {
+        throw_iae(env, "offset/len out of bounds");
+        return Err(());
+    }
+
+    Ok(unsafe { std::slice::from_raw_parts(base_ptr.add(off_u), ln_u) })
+}
+
 #[no_mangle]
 pub extern "system" fn Java_me_naimad_fastregex_FastRegex_compile(
     mut env: JNIEnv,
@@ -43,9 +171,14 @@ pub extern "system" fn Java_me_naimad_fastregex_FastRegex_compile(
         }
     };
 
-    let re_arc: Arc<Regex> = match REGEX_CACHE.get(&pattern) {
-        Some(v) => v,
+    let cache = REGEX_CACHE.load();
+    let re_arc: Arc<Regex> = match cache.get(&pattern) {
+        Some(v) => {
+            CACHE_HITS.fetch_add(1, Ordering::Relaxed);
+            v
+        }
         None => {
+            CACHE_MISSES.fetch_add(1, Ordering::Relaxed);
             let compiled = match Regex::new(&pattern) {
                 Ok(r) => Arc::new(r),
                 Err(e) => {
@@ -53,14 +186,14 @@ pub extern "system" fn Java_me_naimad_fastregex_FastRegex_compile(
                     return 0;
                 }
             };
-            REGEX_CACHE.insert(pattern, compiled.clone());
+            cache.insert(pattern, compiled.clone());
             compiled
         }
     };
 
     let mut table = HANDLES.write();
     let idx = table.insert(re_arc);
-    (idx as jlong) + 1
+    encode_handle(idx)
 }
 
 #[no_mangle]
@@ -88,45 +221,164 @@ pub extern "system" fn Java_me_naimad_fastregex_FastRegex_matchesUtf8Direct(
     offset: jint,
     len: jint,
 ) -> jboolean {
+    let matcher = match resolve_matcher(handle) {
+        Some(m) => m,
+        None => {
+            throw_iae(&mut env, "Unknown/expired handle");
+            return 0;
+        }
+    };
+
+    let slice = match buffer_slice(&mut env, &direct_buf, offset, len) {
+        Ok(s) => s,
+        Err(()) => return 0,
+    };
+
+    let is_match = match matcher {
+        Matcher::Regex(re) => re.is_match(slice),
+        Matcher::Dfa(dfa) => match dfa.try_search_fwd(&Input::new(slice)) {
+            Ok(m) => m.is_some(),
+            Err(e) => {
+                throw_iae(&mut env, &format!("DFA search failed: {e}"));
+                return 0;
+            }
+        },
+    };
+
+    if is_match { 1 } else { 0 }
+}
+
+#[no_mangle]
+pub extern "system" fn Java_me_naimad_fastregex_FastRegex_capturesLen(
+    mut env: JNIEnv,
+    _cls: JClass,
+    handle: jlong,
+) -> jint {
     let re = match get_regex_from_handle(handle) {
         Some(r) => r,
         None => {
             throw_iae(&mut env, "Unknown/expired handle");
-            return 0;
+            return -1;
         }
     };
+    re.captures_len() as jint
+}
 
-    // Optimization: Pull address and capacity once to minimize JNI calls.
-    let base_ptr = match env.get_direct_buffer_address(&direct_buf) {
-        Ok(p) => p,
-        Err(_) => {
-            throw_iae(&mut env, "Buffer is not a DirectByteBuffer");
-            return 0;
+#[no_mangle]
+pub extern "system" fn Java_me_naimad_fastregex_FastRegex_findUtf8Direct(
+    mut env: JNIEnv,
+    _cls: JClass,
+    handle: jlong,
+    direct_buf: JByteBuffer,
+    offset: jint,
+    len: jint,
+    out_span: JIntArray,
+) {
+    let re = match get_regex_from_handle(handle) {
+        Some(r) => r,
+        None => {
+            throw_iae(&mut env, "Unknown/expired handle");
+            return;
         }
     };
-    let cap = match env.get_direct_buffer_capacity(&direct_buf) {
-        Ok(c) => c as usize,
+
+    if match env.get_array_length(&out_span) {
+        Ok(v) => v as usize,
         Err(_) => {
-            throw_iae(&mut env, "Failed to read DirectByteBuffer capacity");
-            return 0;
+            throw_iae(&mut env, "Failed to read outSpan length");
+            return;
         }
+    } < 2
+    {
+        throw_iae(&mut env, "outSpan must have length >= 2");
+        return;
+    }
+
+    let slice = match buffer_slice(&mut env, &direct_buf, offset, len) {
+        Ok(s) => s,
+        Err(()) => return,
     };
 
-    let off_u = offset as usize;
-    let ln_u = len as usize;
+    let (start, end) = match re.find(slice) {
+        Some(m) => (m.start() as jint, m.end() as jint),
+        None => (-1, -1),
+    };
 
-    // Fast unsigned bounds check covering both negative values and overflows.
-    if off_u > cap || ln_u > cap - off_u {
-        throw_iae(&mut env, "offset/len out of bounds");
-        return 0;
+    unsafe {
+        let mut out_auto = match env.get_array_elements(&out_span, ReleaseMode::CopyBack) {
+            Ok(a) => a,
+            Err(_) => {
+                throw_iae(&mut env, "Failed to get outSpan array elements");
+                return;
+            }
+        };
+        let out_slice = &mut *out_auto;
+        out_slice[0] = start;
+        out_slice[1] = end;
+    }
+}
+
+#[no_mangle]
+pub extern "system" fn Java_me_naimad_fastregex_FastRegex_capturesUtf8Direct(
+    mut env: JNIEnv,
+    _cls: JClass,
+    handle: jlong,
+    direct_buf: JByteBuffer,
+    offset: jint,
+    len: jint,
+    out_spans: JIntArray,
+) {
+    let re = match get_regex_from_handle(handle) {
+        Some(r) => r,
+        None => {
+            throw_iae(&mut env, "Unknown/expired handle");
+            return;
+        }
+    };
+
+    let groups = re.captures_len();
+    let needed = groups * 2;
+    let out_len = match env.get_array_length(&out_spans) {
+        Ok(v) => v as usize,
+        Err(_) => {
+            throw_iae(&mut env, "Failed to read outSpans length");
+            return;
+        }
+    };
+    if out_len < needed {
+        throw_iae(&mut env, "outSpans too small");
+        return;
     }
 
-    // Optimization: Direct pointer addition for slice creation to avoid extra slicing overhead.
-    // Safety: direct_buf is a DirectByteBuffer, base_ptr is its starting address, cap is its capacity.
-    // off_u and ln_u are validated to be within [0, cap].
-    let slice = unsafe { std::slice::from_raw_parts(base_ptr.add(off_u), ln_u) };
+    let slice = match buffer_slice(&mut env, &direct_buf, offset, len) {
+        Ok(s) => s,
+        Err(()) => return,
+    };
+
+    let caps = re.captures(slice);
 
-    if re.is_match(slice) { 1 } else { 0 }
+    unsafe {
+        let mut out_auto = match env.get_array_elements(&out_spans, ReleaseMode::CopyBack) {
+            Ok(a) => a,
+            Err(_) => {
+                throw_iae(&mut env, "Failed to get outSpans array elements");
+                return;
+            }
+        };
+        let out_slice = &mut *out_auto;
+
+        for i in 0..groups {
+            let (start, end) = match &caps {
+                Some(c) => match c.get(i) {
+                    Some(m) => (m.start() as jint, m.end() as jint),
+                    None => (-1, -1),
+                },
+                None => (-1, -1),
+            };
+            out_slice[2 * i] = start;
+            out_slice[2 * i + 1] = end;
+        }
+    }
 }
 
 #[no_mangle]
@@ -138,6 +390,7 @@ pub extern "system" fn Java_me_naimad_fastregex_FastRegex_batchMatchesUtf8Direct
     offsets: JIntArray,
     lengths: JIntArray,
     out_bits: JLongArray,
+    parallel: jboolean,
 ) {
     let re = match get_regex_from_handle(handle) {
         Some(r) => r,
@@ -202,7 +455,10 @@ pub extern "system" fn Java_me_naimad_fastregex_FastRegex_batchMatchesUtf8Direct
     // Safety: data_buf is a DirectByteBuffer, base_ptr is its starting address, cap is its capacity.
     let data: &[u8] = unsafe { std::slice::from_raw_parts(base_ptr, cap) };
 
-    unsafe {
+    // `env.get_array_elements` handles are not `Send`, so the rayon path below must not
+    // touch them. Copy offsets/lengths into owned, plain-data buffers up front and drop
+    // the JNI handles before crossing onto the rayon pool.
+    let (offsets_vec, lengths_vec): (Vec<i32>, Vec<i32>) = unsafe {
         let offsets_auto = match env.get_array_elements(&offsets, ReleaseMode::NoCopyBack) {
             Ok(a) => a,
             Err(_) => {
@@ -217,6 +473,43 @@ pub extern "system" fn Java_me_naimad_fastregex_FastRegex_batchMatchesUtf8Direct
                 return;
             }
         };
+        ((*offsets_auto).to_vec(), (*lengths_auto).to_vec())
+    };
+
+    // One word per 64-record chunk, mirroring the outBits bitset layout.
+    let compute_word = |off_chunk: &[i32], len_chunk: &[i32]| -> i64 {
+        let mut word = 0i64;
+        for (bit_idx, (&off, &ln)) in off_chunk.iter().zip(len_chunk.iter()).enumerate() {
+            // Fast unsigned bounds check covers negative values and overflow.
+            let off_u = off as usize;
+            let ln_u = ln as usize;
+
+            if off_u <= cap && ln_u <= cap - off_u {
+                // Safety: off_u and ln_u are within data bounds.
+                let slice = unsafe { data.get_unchecked(off_u..off_u + ln_u) };
+                if re.is_match(slice) {
+                    word |= 1i64 << bit_idx;
+                }
+            }
+        }
+        word
+    };
+
+    let words: Vec<i64> = if parallel != 0 {
+        offsets_vec
+            .par_chunks(64)
+            .zip(lengths_vec.par_chunks(64))
+            .map(|(off_chunk, len_chunk)| compute_word(off_chunk, len_chunk))
+            .collect()
+    } else {
+        offsets_vec
+            .chunks(64)
+            .zip(lengths_vec.chunks(64))
+            .map(|(off_chunk, len_chunk)| compute_word(off_chunk, len_chunk))
+            .collect()
+    };
+
+    unsafe {
         let mut out_bits_auto = match env.get_array_elements(&out_bits, ReleaseMode::CopyBack) {
             Ok(a) => a,
             Err(_) => {
@@ -224,31 +517,895 @@ pub extern "system" fn Java_me_naimad_fastregex_FastRegex_batchMatchesUtf8Direct
                 return;
             }
         };
-
-        // Use slices for faster access and to enable compiler optimizations like auto-vectorization.
-        let offsets_slice = &*offsets_auto;
-        let lengths_slice = &*lengths_auto;
         let out_bits_slice = &mut *out_bits_auto;
+        out_bits_slice[..needed_words].copy_from_slice(&words);
+    }
+}
 
-        // Optimization: Use chunks(64) to iterate over bitset words, simplifying loops and bit logic.
-        // Using zip() for offsets and lengths allows the compiler to optimize access patterns.
-        for (word_idx, (off_chunk, len_chunk)) in offsets_slice.chunks(64).zip(lengths_slice.chunks(64)).enumerate() {
-            let mut word = 0i64;
-            for (bit_idx, (&off, &ln)) in off_chunk.iter().zip(len_chunk.iter()).enumerate() {
-                // Optimization: Fast unsigned bounds check covers negative values and overflow.
-                let off_u = off as usize;
-                let ln_u = ln as usize;
-
-                if off_u <= cap && ln_u <= cap - off_u {
-                    // Safety: off_u and ln_u are within data bounds.
-                    let slice = data.get_unchecked(off_u..off_u + ln_u);
-                    if re.is_match(slice) {
-                        word |= 1i64 << bit_idx;
-                    }
+#[no_mangle]
+pub extern "system" fn Java_me_naimad_fastregex_FastRegex_compileSet(
+    mut env: JNIEnv,
+    _cls: JClass,
+    patterns_obj: JObjectArray,
+) -> jlong {
+    let n = match env.get_array_length(&patterns_obj) {
+        Ok(v) => v,
+        Err(_) => {
+            throw_iae(&mut env, "Failed to read patterns length");
+            return 0;
+        }
+    };
+
+    let mut patterns: Vec<String> = Vec::with_capacity(n as usize);
+    for i in 0..n {
+        let elem = match env.get_object_array_element(&patterns_obj, i) {
+            Ok(o) => o,
+            Err(_) => {
+                throw_iae(&mut env, "Failed to read pattern element");
+                return 0;
+            }
+        };
+        let jstr: JString = elem.into();
+        let s: String = match env.get_string(&jstr) {
+            Ok(s) => s.into(),
+            Err(_) => {
+                throw_iae(&mut env, "Failed to read pattern string");
+                return 0;
+            }
+        };
+        patterns.push(s);
+    }
+
+    let set_cache = REGEXSET_CACHE.load();
+    let set_arc: Arc<RegexSet> = match set_cache.get(&patterns) {
+        Some(v) => {
+            SET_CACHE_HITS.fetch_add(1, Ordering::Relaxed);
+            v
+        }
+        None => {
+            SET_CACHE_MISSES.fetch_add(1, Ordering::Relaxed);
+            let compiled = match RegexSet::new(&patterns) {
+                Ok(r) => Arc::new(r),
+                Err(e) => {
+                    throw_iae(&mut env, &format!("Invalid regex set: {e}"));
+                    return 0;
                 }
+            };
+            set_cache.insert(patterns, compiled.clone());
+            compiled
+        }
+    };
+
+    let mut table = SET_HANDLES.write();
+    let idx = table.insert(set_arc);
+    encode_set_handle(idx)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_me_naimad_fastregex_FastRegex_releaseSet(
+    mut env: JNIEnv,
+    _cls: JClass,
+    handle: jlong,
+) {
+    let Some(idx) = set_handle_to_index(handle) else {
+        throw_iae(&mut env, "Invalid handle");
+        return;
+    };
+    let mut table = SET_HANDLES.write();
+    if table.contains(idx) {
+        table.remove(idx);
+    }
+}
+
+#[no_mangle]
+pub extern "system" fn Java_me_naimad_fastregex_FastRegex_setMatchesUtf8Direct(
+    mut env: JNIEnv,
+    _cls: JClass,
+    handle: jlong,
+    direct_buf: JByteBuffer,
+    offset: jint,
+    len: jint,
+    out_bits: JLongArray,
+) {
+    let set = match get_regexset_from_handle(handle) {
+        Some(s) => s,
+        None => {
+            throw_iae(&mut env, "Unknown/expired handle");
+            return;
+        }
+    };
+
+    let needed_words = (set.len() + 63) / 64;
+    let out_len = match env.get_array_length(&out_bits) {
+        Ok(v) => v as usize,
+        Err(_) => {
+            throw_iae(&mut env, "Failed to read outBits length");
+            return;
+        }
+    };
+    if out_len < needed_words {
+        throw_iae(&mut env, "outBits too small");
+        return;
+    }
+
+    let slice = match buffer_slice(&mut env, &direct_buf, offset, len) {
+        Ok(s) => s,
+        Err(()) => return,
+    };
+
+    let mut words = vec![0i64; needed_words];
+    for idx in set.matches(slice).iter() {
+        words[idx / 64] |= 1i64 << (idx % 64);
+    }
+
+    unsafe {
+        let mut out_auto = match env.get_array_elements(&out_bits, ReleaseMode::CopyBack) {
+            Ok(a) => a,
+            Err(_) => {
+                throw_iae(&mut env, "Failed to get outBits array elements");
+                return;
             }
-            // word_idx < needed_words <= out_bits_slice.len() is guaranteed.
-            *out_bits_slice.get_unchecked_mut(word_idx) = word;
+        };
+        let out_slice = &mut *out_auto;
+        out_slice[..needed_words].copy_from_slice(&words);
+    }
+}
+
+#[no_mangle]
+pub extern "system" fn Java_me_naimad_fastregex_FastRegex_batchSetMatchesUtf8Direct(
+    mut env: JNIEnv,
+    _cls: JClass,
+    handle: jlong,
+    data_buf: JByteBuffer,
+    offsets: JIntArray,
+    lengths: JIntArray,
+    out_bits: JLongArray,
+) {
+    let set = match get_regexset_from_handle(handle) {
+        Some(s) => s,
+        None => {
+            throw_iae(&mut env, "Unknown/expired handle");
+            return;
         }
+    };
+
+    let base_ptr = match env.get_direct_buffer_address(&data_buf) {
+        Ok(p) => p,
+        Err(_) => {
+            throw_iae(&mut env, "dataBuf is not a DirectByteBuffer");
+            return;
+        }
+    };
+    let cap = match env.get_direct_buffer_capacity(&data_buf) {
+        Ok(c) => c as usize,
+        Err(_) => {
+            throw_iae(&mut env, "Failed to read dataBuf capacity");
+            return;
+        }
+    };
+
+    let n = match env.get_array_length(&offsets) {
+        Ok(v) => v as usize,
+        Err(_) => {
+            throw_iae(&mut env, "Failed to read offsets length");
+            return;
+        }
+    };
+
+    let n_len = match env.get_array_length(&lengths) {
+        Ok(v) => v as usize,
+        Err(_) => {
+            throw_iae(&mut env, "Failed to read lengths length");
+            return;
+        }
+    };
+
+    if n != n_len {
+        throw_iae(&mut env, "offsets.length != lengths.length");
+        return;
+    }
+
+    let needed_words = (set.len() + 63) / 64;
+    let out_len = match env.get_array_length(&out_bits) {
+        Ok(v) => v as usize,
+        Err(_) => {
+            throw_iae(&mut env, "Failed to read outBits length");
+            return;
+        }
+    };
+    if out_len < n * needed_words {
+        throw_iae(&mut env, "outBits too small");
+        return;
+    }
+
+    // Safety: data_buf is a DirectByteBuffer, base_ptr is its starting address, cap is its capacity.
+    let data: &[u8] = unsafe { std::slice::from_raw_parts(base_ptr, cap) };
+
+    unsafe {
+        let offsets_auto = match env.get_array_elements(&offsets, ReleaseMode::NoCopyBack) {
+            Ok(a) => a,
+            Err(_) => {
+                throw_iae(&mut env, "Failed to get offsets array elements");
+                return;
+            }
+        };
+        let lengths_auto = match env.get_array_elements(&lengths, ReleaseMode::NoCopyBack) {
+            Ok(a) => a,
+            Err(_) => {
+                throw_iae(&mut env, "Failed to get lengths array elements");
+                return;
+            }
+        };
+        let mut out_bits_auto = match env.get_array_elements(&out_bits, ReleaseMode::CopyBack) {
+            Ok(a) => a,
+            Err(_) => {
+                throw_iae(&mut env, "Failed to get outBits array elements");
+                return;
+            }
+        };
+
+        let offsets_slice = &*offsets_auto;
+        let lengths_slice = &*lengths_auto;
+        let out_bits_slice = &mut *out_bits_auto;
+        out_bits_slice[..n * needed_words].fill(0);
+
+        for (i, (&off, &ln)) in offsets_slice.iter().zip(lengths_slice.iter()).enumerate() {
+            let row = i * needed_words;
+            // Fast unsigned bounds check covers negative values and overflow.
+            let off_u = off as usize;
+            let ln_u = ln as usize;
+
+            if off_u <= cap && ln_u <= cap - off_u {
+                // Safety: off_u and ln_u are within data bounds.
+                let record = data.get_unchecked(off_u..off_u + ln_u);
+                for idx in set.matches(record).iter() {
+                    let word_idx = row + idx / 64;
+                    *out_bits_slice.get_unchecked_mut(word_idx) |= 1i64 << (idx % 64);
+                }
+            }
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "system" fn Java_me_naimad_fastregex_FastRegex_buildDfa(
+    mut env: JNIEnv,
+    _cls: JClass,
+    pattern_obj: jni::objects::JString,
+    out_path_obj: jni::objects::JString,
+) {
+    let pattern: String = match env.get_string(&pattern_obj) {
+        Ok(s) => s.into(),
+        Err(_) => {
+            throw_iae(&mut env, "Failed to read pattern string");
+            return;
+        }
+    };
+    let out_path: String = match env.get_string(&out_path_obj) {
+        Ok(s) => s.into(),
+        Err(_) => {
+            throw_iae(&mut env, "Failed to read outPath string");
+            return;
+        }
+    };
+
+    let dfa = match DFA::new(&pattern) {
+        Ok(d) => d,
+        Err(e) => {
+            throw_iae(&mut env, &format!("Invalid regex: {e}"));
+            return;
+        }
+    };
+
+    // `to_bytes_native_endian` returns the serialized bytes alongside the length of the
+    // leading padding it inserted to align the in-process `Vec`'s own buffer; that padding
+    // only makes sense at the address the `Vec` happened to land at, not at whatever
+    // address the file is later mapped to, so it must be sliced off before writing.
+    let (bytes, padding) = dfa.to_bytes_native_endian();
+    if let Err(e) = std::fs::write(&out_path, &bytes[padding..]) {
+        throw_iae(&mut env, &format!("Failed to write DFA to {out_path}: {e}"));
+    }
+}
+
+#[no_mangle]
+pub extern "system" fn Java_me_naimad_fastregex_FastRegex_loadDfa(
+    mut env: JNIEnv,
+    _cls: JClass,
+    mapped_file: JByteBuffer,
+) -> jlong {
+    let base_ptr = match env.get_direct_buffer_address(&mapped_file) {
+        Ok(p) => p,
+        Err(_) => {
+            throw_iae(&mut env, "mappedFile is not a DirectByteBuffer");
+            return 0;
+        }
+    };
+    let cap = match env.get_direct_buffer_capacity(&mapped_file) {
+        Ok(c) => c as usize,
+        Err(_) => {
+            throw_iae(&mut env, "Failed to read mappedFile capacity");
+            return 0;
+        }
+    };
+
+    // `DFA::from_bytes` requires the slice to start on a `u32`-aligned address.
+    if !(base_ptr as usize).is_multiple_of(std::mem::align_of::<u32>()) {
+        throw_iae(&mut env, "mappedFile must be 4-byte (u32) aligned");
+        return 0;
+    }
+
+    // Safety: mapped_file is a DirectByteBuffer of cap bytes starting at base_ptr. The
+    // caller owns the backing mapping and must keep it alive for as long as the returned
+    // handle is in use, the same zero-copy contract every *Utf8Direct entry point relies on.
+    let bytes: &'static [u8] = unsafe { std::slice::from_raw_parts(base_ptr, cap) };
+
+    // `from_bytes` validates the serialized header and transition table itself; the image
+    // carries no checksum, so no checksum is verified here.
+    let dfa = match DFA::from_bytes(bytes) {
+        Ok((d, _)) => d,
+        Err(e) => {
+            throw_iae(&mut env, &format!("Invalid DFA image: {e}"));
+            return 0;
+        }
+    };
+
+    let mut table = DFA_HANDLES.write();
+    let idx = table.insert(Arc::new(dfa));
+    -(idx as jlong + 1)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_me_naimad_fastregex_FastRegex_releaseDfa(
+    mut env: JNIEnv,
+    _cls: JClass,
+    handle: jlong,
+) {
+    let Some(idx) = dfa_handle_to_index(handle) else {
+        throw_iae(&mut env, "Invalid handle");
+        return;
+    };
+    let mut table = DFA_HANDLES.write();
+    if table.contains(idx) {
+        table.remove(idx);
+    }
+}
+
+#[no_mangle]
+pub extern "system" fn Java_me_naimad_fastregex_FastRegex_findAllUtf8Direct(
+    mut env: JNIEnv,
+    _cls: JClass,
+    handle: jlong,
+    direct_buf: JByteBuffer,
+    offset: jint,
+    len: jint,
+    out_spans: JIntArray,
+    max_matches: jint,
+) -> jint {
+    let re = match get_regex_from_handle(handle) {
+        Some(r) => r,
+        None => {
+            throw_iae(&mut env, "Unknown/expired handle");
+            return 0;
+        }
+    };
+
+    if max_matches < 0 {
+        throw_iae(&mut env, "maxMatches must be >= 0");
+        return 0;
+    }
+    let max_matches = max_matches as usize;
+
+    let out_len = match env.get_array_length(&out_spans) {
+        Ok(v) => v as usize,
+        Err(_) => {
+            throw_iae(&mut env, "Failed to read outSpans length");
+            return 0;
+        }
+    };
+    if out_len < 2 * max_matches {
+        throw_iae(&mut env, "outSpans too small for maxMatches");
+        return 0;
+    }
+
+    let slice = match buffer_slice(&mut env, &direct_buf, offset, len) {
+        Ok(s) => s,
+        Err(()) => return 0,
+    };
+
+    // Spans are relative to `slice` (i.e. to `offset`), matching findUtf8Direct/capturesUtf8Direct.
+    let spans: Vec<(jint, jint)> = re
+        .find_iter(slice)
+        .take(max_matches)
+        .map(|m| (m.start() as jint, m.end() as jint))
+        .collect();
+    let count = spans.len();
+
+    unsafe {
+        let mut out_auto = match env.get_array_elements(&out_spans, ReleaseMode::CopyBack) {
+            Ok(a) => a,
+            Err(_) => {
+                throw_iae(&mut env, "Failed to get outSpans array elements");
+                return 0;
+            }
+        };
+        let out_slice = &mut *out_auto;
+        for (i, (start, end)) in spans.into_iter().enumerate() {
+            out_slice[2 * i] = start;
+            out_slice[2 * i + 1] = end;
+        }
+    }
+
+    count as jint
+}
+
+#[no_mangle]
+pub extern "system" fn Java_me_naimad_fastregex_FastRegex_matchesAnchoredUtf8Direct(
+    mut env: JNIEnv,
+    _cls: JClass,
+    handle: jlong,
+    direct_buf: JByteBuffer,
+    offset: jint,
+    len: jint,
+) -> jboolean {
+    let matcher = match resolve_matcher(handle) {
+        Some(m) => m,
+        None => {
+            throw_iae(&mut env, "Unknown/expired handle");
+            return 0;
+        }
+    };
+
+    let slice = match buffer_slice(&mut env, &direct_buf, offset, len) {
+        Ok(s) => s,
+        Err(()) => return 0,
+    };
+
+    let is_match = match matcher {
+        // `find` is leftmost: if any match begins at offset 0 of the slice, it is the
+        // leftmost one, so this is equivalent to requiring the match start at `offset`.
+        Matcher::Regex(re) => re.find(slice).is_some_and(|m| m.start() == 0),
+        Matcher::Dfa(dfa) => {
+            let input = Input::new(slice).anchored(Anchored::Yes);
+            match dfa.try_search_fwd(&input) {
+                Ok(m) => m.is_some(),
+                Err(e) => {
+                    throw_iae(&mut env, &format!("DFA search failed: {e}"));
+                    return 0;
+                }
+            }
+        }
+    };
+
+    if is_match { 1 } else { 0 }
+}
+
+// Earliest-match search is a dense-DFA-only knob (`Input::earliest`); the `regex` crate's
+// `Regex`/`RegexSet` have no equivalent, so unlike matchesUtf8Direct/matchesAnchoredUtf8Direct
+// this one does not fall back to ordinary compile()-produced handles.
+#[no_mangle]
+pub extern "system" fn Java_me_naimad_fastregex_FastRegex_matchesEarliestUtf8Direct(
+    mut env: JNIEnv,
+    _cls: JClass,
+    handle: jlong,
+    direct_buf: JByteBuffer,
+    offset: jint,
+    len: jint,
+) -> jboolean {
+    let dfa = match get_dfa_from_handle(handle) {
+        Some(d) => d,
+        None => {
+            throw_iae(
+                &mut env,
+                "Earliest mode requires a DFA handle from buildDfa/loadDfa",
+            );
+            return 0;
+        }
+    };
+
+    let slice = match buffer_slice(&mut env, &direct_buf, offset, len) {
+        Ok(s) => s,
+        Err(()) => return 0,
+    };
+
+    let input = Input::new(slice).earliest(true);
+    match dfa.try_search_fwd(&input) {
+        Ok(m) => {
+            if m.is_some() {
+                1
+            } else {
+                0
+            }
+        }
+        Err(e) => {
+            throw_iae(&mut env, &format!("DFA search failed: {e}"));
+            0
+        }
+    }
+}
+
+// Replacement handles share the compiled-pattern cache and handle table used by `compile`;
+// this entry point exists so Java call sites doing search-and-replace don't need to reach
+// through the match-only `compile`/`matchesUtf8Direct` pairing.
+#[no_mangle]
+pub extern "system" fn Java_me_naimad_fastregex_FastRegex_compileReplacement(
+    env: JNIEnv,
+    cls: JClass,
+    pattern_obj: JString,
+) -> jlong {
+    Java_me_naimad_fastregex_FastRegex_compile(env, cls, pattern_obj)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_me_naimad_fastregex_FastRegex_replaceAllUtf8Direct(
+    mut env: JNIEnv,
+    _cls: JClass,
+    handle: jlong,
+    src_buf: JByteBuffer,
+    src_off: jint,
+    src_len: jint,
+    replacement: JByteArray,
+    dst_buf: JByteBuffer,
+    dst_cap: jint,
+) -> jint {
+    let re = match get_regex_from_handle(handle) {
+        Some(r) => r,
+        None => {
+            throw_iae(&mut env, "Unknown/expired handle");
+            return 0;
+        }
+    };
+
+    let src_slice = match buffer_slice(&mut env, &src_buf, src_off, src_len) {
+        Ok(s) => s,
+        Err(()) => return 0,
+    };
+
+    if dst_cap < 0 {
+        throw_iae(&mut env, "dstCap must be >= 0");
+        return 0;
+    }
+    let dst_cap_u = dst_cap as usize;
+
+    let dst_ptr = match env.get_direct_buffer_address(&dst_buf) {
+        Ok(p) => p,
+        Err(_) => {
+            throw_iae(&mut env, "dstBuffer is not a DirectByteBuffer");
+            return 0;
+        }
+    };
+    let dst_buf_cap = match env.get_direct_buffer_capacity(&dst_buf) {
+        Ok(c) => c as usize,
+        Err(_) => {
+            throw_iae(&mut env, "Failed to read dstBuffer capacity");
+            return 0;
+        }
+    };
+    if dst_cap_u > dst_buf_cap {
+        throw_iae(&mut env, "dstCap exceeds dstBuffer capacity");
+        return 0;
+    }
+
+    let replacement_template = match env.convert_byte_array(&replacement) {
+        Ok(v) => v,
+        Err(_) => {
+            throw_iae(&mut env, "Failed to read replacement byte array");
+            return 0;
+        }
+    };
+
+    // `&[u8]` implements `Replacer` for the bytes flavor of Regex, expanding `$1`/`${name}`
+    // capture references against the template.
+    let rewritten = re.replace_all(src_slice, replacement_template.as_slice());
+
+    if rewritten.len() > dst_cap_u {
+        return -(rewritten.len() as jint);
+    }
+
+    // When the pattern doesn't match, `replace_all` returns `Cow::Borrowed(src_slice)`, so
+    // `rewritten.as_ptr()` can alias `dst_ptr` for an in-place rewrite (same/overlapping
+    // src/dst buffers). Use `copy` (memmove semantics) rather than `copy_nonoverlapping`
+    // so that overlap is handled correctly instead of being UB.
+    //
+    // Safety: dst_ptr is dstBuffer's starting address and dst_cap_u <= dstBuffer's capacity,
+    // and rewritten.len() <= dst_cap_u was just checked above.
+    unsafe {
+        std::ptr::copy(rewritten.as_ptr(), dst_ptr, rewritten.len());
+    }
+
+    rewritten.len() as jint
+}
+
+#[no_mangle]
+pub extern "system" fn Java_me_naimad_fastregex_FastRegex_configureCache(
+    mut env: JNIEnv,
+    _cls: JClass,
+    max_capacity: jlong,
+    ttl_seconds: jlong,
+    tti_seconds: jlong,
+) {
+    if max_capacity < 0 || ttl_seconds < 0 || tti_seconds < 0 {
+        throw_iae(
+            &mut env,
+            "maxCapacity/ttlSeconds/ttiSeconds must be >= 0",
+        );
+        return;
+    }
+
+    let cache = moka::sync::Cache::builder()
+        .max_capacity(max_capacity as u64)
+        .time_to_live(Duration::from_secs(ttl_seconds as u64))
+        .time_to_idle(Duration::from_secs(tti_seconds as u64))
+        .build();
+    let set_cache = moka::sync::Cache::builder()
+        .max_capacity(max_capacity as u64)
+        .time_to_live(Duration::from_secs(ttl_seconds as u64))
+        .time_to_idle(Duration::from_secs(tti_seconds as u64))
+        .build();
+
+    // Handles already issued by `compile`/`compileSet` hold their own `Arc<Regex>`/
+    // `Arc<RegexSet>` clone in `HANDLES`/`SET_HANDLES`, so swapping either cache here
+    // never invalidates a live handle; it only changes what future `compile`/`compileSet`
+    // calls find on lookup. Both caches share the same bound/TTL/TTI configuration since
+    // they have the same unbounded-growth risk.
+    REGEX_CACHE.store(Arc::new(cache));
+    REGEXSET_CACHE.store(Arc::new(set_cache));
+}
+
+// out[0..2) is REGEX_CACHE's (hits, misses); out[2..4) is REGEXSET_CACHE's, in the same
+// order. Kept as one entry point (rather than a second cacheStats variant) since callers
+// tuning configureCache want both caches' hit rates together.
+#[no_mangle]
+pub extern "system" fn Java_me_naimad_fastregex_FastRegex_cacheStats(
+    mut env: JNIEnv,
+    _cls: JClass,
+    out: JLongArray,
+) {
+    let out_len = match env.get_array_length(&out) {
+        Ok(v) => v as usize,
+        Err(_) => {
+            throw_iae(&mut env, "Failed to read out length");
+            return;
+        }
+    };
+    if out_len < 4 {
+        throw_iae(&mut env, "out must have length >= 4");
+        return;
+    }
+
+    let hits = CACHE_HITS.load(Ordering::Relaxed) as jlong;
+    let misses = CACHE_MISSES.load(Ordering::Relaxed) as jlong;
+    let set_hits = SET_CACHE_HITS.load(Ordering::Relaxed) as jlong;
+    let set_misses = SET_CACHE_MISSES.load(Ordering::Relaxed) as jlong;
+    let _ = env.set_long_array_region(&out, 0, &[hits, misses, set_hits, set_misses]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A handle returned by `compile` holds its own `Arc<Regex>` clone in `HANDLES`,
+    // independent of `REGEX_CACHE`. Reconfiguring (and thus replacing) the cache must not
+    // break handles issued before the swap.
+    #[test]
+    fn in_use_handle_survives_cache_eviction() {
+        let pattern = "fastregex-cache-eviction-test".to_string();
+
+        let cache = REGEX_CACHE.load();
+        let compiled = Arc::new(Regex::new(&pattern).unwrap());
+        cache.insert(pattern.clone(), compiled.clone());
+
+        let idx = HANDLES.write().insert(compiled);
+        let handle = encode_handle(idx);
+
+        // Swap in a tiny, already-expired cache: this evicts `pattern` from the cache
+        // (distinct from `HANDLES`) without touching any issued handle.
+        let tiny = moka::sync::Cache::builder()
+            .max_capacity(0)
+            .time_to_live(Duration::from_secs(0))
+            .build();
+        REGEX_CACHE.store(Arc::new(tiny));
+        REGEX_CACHE.load().run_pending_tasks();
+
+        assert!(REGEX_CACHE.load().get(&pattern).is_none());
+
+        let re = get_regex_from_handle(handle).expect("handle should survive cache eviction");
+        assert!(re.is_match(b"fastregex-cache-eviction-test"));
+
+        HANDLES.write().remove(idx);
+    }
+
+    // Same safety claim as in_use_handle_survives_cache_eviction, but for the RegexSet/
+    // SET_HANDLES/REGEXSET_CACHE trio introduced alongside REGEX_CACHE's own reconfigurable
+    // cache: a handle returned by `compileSet` must survive `configureCache` evicting the
+    // REGEXSET_CACHE entry it came from.
+    #[test]
+    fn in_use_set_handle_survives_cache_eviction() {
+        let patterns = vec!["fastregex-set-eviction-test".to_string()];
+
+        let set_cache = REGEXSET_CACHE.load();
+        let compiled = Arc::new(RegexSet::new(&patterns).unwrap());
+        set_cache.insert(patterns.clone(), compiled.clone());
+
+        let idx = SET_HANDLES.write().insert(compiled);
+        let handle = encode_set_handle(idx);
+
+        // Swap in a tiny, already-expired cache: this evicts `patterns` from the cache
+        // (distinct from `SET_HANDLES`) without touching any issued handle.
+        let tiny = moka::sync::Cache::builder()
+            .max_capacity(0)
+            .time_to_live(Duration::from_secs(0))
+            .build();
+        REGEXSET_CACHE.store(Arc::new(tiny));
+        REGEXSET_CACHE.load().run_pending_tasks();
+
+        assert!(REGEXSET_CACHE.load().get(&patterns).is_none());
+
+        let set = get_regexset_from_handle(handle).expect("handle should survive cache eviction");
+        assert!(set.is_match(b"fastregex-set-eviction-test"));
+
+        SET_HANDLES.write().remove(idx);
+    }
+
+    // findUtf8Direct/capturesUtf8Direct report spans computed via re.find(slice)/
+    // re.captures(slice) over the zero-copy slice; pin those span values against what
+    // regex::bytes::Regex itself reports for the same pattern/haystack.
+    #[test]
+    fn span_extraction_matches_regex_bytes_crate() {
+        let re = Regex::new(r"(\d+)-(\w+)").unwrap();
+        let hay = b"order 42-widgets shipped";
+
+        // What findUtf8Direct writes into outSpan.
+        let m = re.find(hay).unwrap();
+        assert_eq!((m.start(), m.end()), (6, 16));
+
+        // What capturesUtf8Direct writes into outSpans, one (start, end) pair per group.
+        let caps = re.captures(hay).unwrap();
+        assert_eq!(caps.get(0).map(|g| (g.start(), g.end())), Some((6, 16)));
+        assert_eq!(caps.get(1).map(|g| (g.start(), g.end())), Some((6, 8)));
+        assert_eq!(caps.get(2).map(|g| (g.start(), g.end())), Some((9, 16)));
+    }
+
+    // setMatchesUtf8Direct packs set.matches() into outBits via `words[idx / 64] |= 1 <<
+    // (idx % 64)`; exercise a set big enough that a match index (64) lands in the second
+    // word to prove the division/modulo split is correct at the boundary, not just for
+    // idx < 64.
+    #[test]
+    fn set_matches_bitset_packs_across_word_boundary() {
+        let mut patterns: Vec<String> = (0..65).map(|i| format!("never-matches-{i}")).collect();
+        patterns[63] = "boundary-lo".to_string();
+        patterns[64] = "boundary-hi".to_string();
+        let set = RegexSet::new(&patterns).unwrap();
+
+        let needed_words = (set.len() + 63) / 64;
+        assert_eq!(needed_words, 2);
+
+        let mut words = vec![0i64; needed_words];
+        for idx in set.matches(b"boundary-lo and boundary-hi").iter() {
+            words[idx / 64] |= 1i64 << (idx % 64);
+        }
+
+        assert_eq!(words[0], 1i64 << 63);
+        assert_eq!(words[1], 1i64 << 0);
+    }
+
+    // buildDfa writes `&bytes[padding..]` to a file; loadDfa mmaps that file back and
+    // passes it straight to `DFA::from_bytes`. Exercise that exact write/read contract
+    // in-process (skipping only the JNI/mmap plumbing, which isn't available in a unit
+    // test) to prove the padding is sliced off consistently on both ends.
+    #[test]
+    fn build_dfa_round_trips_through_load_dfa() {
+        let dfa = DFA::new("fastregex-(dfa|automaton)-roundtrip").unwrap();
+        let (bytes, padding) = dfa.to_bytes_native_endian();
+        let written = bytes[padding..].to_vec();
+
+        let (loaded, _) = DFA::from_bytes(&written).expect("DFA image should reload");
+
+        let hit = Input::new(b"the fastregex-dfa-roundtrip test");
+        let miss = Input::new(b"no match here");
+        assert!(loaded.try_search_fwd(&hit).unwrap().is_some());
+        assert!(loaded.try_search_fwd(&miss).unwrap().is_none());
+    }
+
+    // findAllUtf8Direct enumerates non-overlapping matches via `re.find_iter(slice)`; prove
+    // it actually walks past the first match instead of stopping there.
+    #[test]
+    fn find_all_enumerates_every_match() {
+        let re = Regex::new(r"\d+").unwrap();
+        let hay = b"a1 b22 c333";
+
+        let spans: Vec<(usize, usize)> = re.find_iter(hay).map(|m| (m.start(), m.end())).collect();
+
+        assert_eq!(spans, vec![(1, 2), (4, 6), (8, 11)]);
+    }
+
+    // matchesAnchoredUtf8Direct treats `find(slice).is_some_and(|m| m.start() == 0)` as
+    // "anchored match"; a pattern that only matches later in the slice must be rejected
+    // even though an unanchored search over the same slice would succeed.
+    #[test]
+    fn anchored_match_rejects_match_not_at_offset() {
+        let re = Regex::new(r"widgets").unwrap();
+        let hay = b"order widgets";
+
+        assert!(re.is_match(hay));
+        assert!(!re.find(hay).is_some_and(|m| m.start() == 0));
+
+        let anchored_hay = b"widgets order";
+        assert!(re.find(anchored_hay).is_some_and(|m| m.start() == 0));
+    }
+
+    // batchMatchesUtf8Direct's `compute_word` closure is shared by both the `parallel`
+    // (`par_chunks`) and sequential (`chunks`) code paths; run the same records/lengths
+    // through both chunking strategies and confirm they produce identical bitset words.
+    #[test]
+    fn parallel_and_sequential_batch_matches_agree() {
+        let re = Regex::new(r"^\d+$").unwrap();
+
+        let records: Vec<&[u8]> = vec![
+            b"1", b"22", b"333", b"abc", b"4444", b"x", b"55555", b"666666", b"z", b"zz",
+            b"7777777",
+        ];
+        // Pad to exercise a word boundary, mirroring the 64-records-per-u64-word layout.
+        let mut recs = records.clone();
+        while recs.len() < 70 {
+            recs.push(b"nope");
+        }
+
+        let compute_word = |chunk: &[&[u8]]| -> i64 {
+            let mut word = 0i64;
+            for (bit_idx, rec) in chunk.iter().enumerate() {
+                if re.is_match(rec) {
+                    word |= 1i64 << bit_idx;
+                }
+            }
+            word
+        };
+
+        let sequential: Vec<i64> = recs.chunks(64).map(compute_word).collect();
+        let parallel: Vec<i64> = recs.par_chunks(64).map(compute_word).collect();
+
+        assert_eq!(sequential, parallel);
+        assert_eq!(sequential.len(), 2);
+    }
+
+    // replaceAllUtf8Direct returns `-(rewritten.len())` when dstCap is too small, without
+    // writing anything, so the caller can retry with a buffer sized from that negative
+    // return value.
+    #[test]
+    fn replace_all_reports_needed_capacity_on_undersized_dst() {
+        let re = Regex::new(r"\d+").unwrap();
+        let src = b"room 7 and room 42";
+        let rewritten = re.replace_all(src, &b"N"[..]);
+
+        let dst_cap_u = 4usize; // too small for the rewritten "room N and room N"
+        assert!(rewritten.len() > dst_cap_u);
+        assert_eq!(&*rewritten, &b"room N and room N"[..]);
+
+        // `replaceAllUtf8Direct` returns `-(rewritten.len())`; retrying with a buffer
+        // sized from `-reported` must fit exactly.
+        let reported = -(rewritten.len() as i32);
+        let retry_cap = (-reported) as usize;
+        assert_eq!(retry_cap, rewritten.len());
+    }
+
+    // When the pattern doesn't match, `replace_all` returns `Cow::Borrowed(src_slice)`, so
+    // an in-place rewrite (src and dst are the same buffer) has `rewritten.as_ptr()` alias
+    // `dst_ptr` exactly: `copy` (memmove semantics) must leave the buffer untouched, where
+    // `copy_nonoverlapping` would be UB on the same source/destination pointer.
+    #[test]
+    fn replace_all_in_place_no_match_self_copy_is_safe() {
+        let re = Regex::new(r"cat").unwrap();
+        let mut buf = b"a dog sat".to_vec();
+
+        let rewritten = re.replace_all(&buf, &b"dog"[..]);
+        assert!(matches!(rewritten, std::borrow::Cow::Borrowed(_)));
+        assert_eq!(rewritten.as_ptr(), buf.as_ptr());
+
+        let len = rewritten.len();
+        let ptr = buf.as_mut_ptr();
+        unsafe {
+            std::ptr::copy(ptr, ptr, len);
+        }
+
+        assert_eq!(buf, b"a dog sat");
     }
 }
\ No newline at end of file